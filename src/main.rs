@@ -1,13 +1,33 @@
-use std::{env, net::SocketAddr, time::SystemTime};
+use std::{
+    env,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use axum::{extract::State, http::HeaderValue, response::Response, routing::any, Router};
+use axum::{
+    extract::State, http::HeaderValue, response::IntoResponse, response::Response, routing::any,
+    Router,
+};
+use futures::StreamExt;
 use hyper::{
     client::{connect::Connect, HttpConnector},
     Body, Client, Request, StatusCode, Uri,
 };
 use hyper_proxy::{Intercept, Proxy, ProxyConnector};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
-use libflate::gzip::Decoder;
+use opentelemetry::{
+    metrics::{Counter, Histogram, Meter},
+    KeyValue,
+};
+use prometheus::{Encoder, Registry, TextEncoder};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    ServerConfig,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
 use tracing::{info, log::warn};
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
@@ -17,6 +37,244 @@ enum ClientEnum {
     Http(Client<HttpsConnector<HttpConnector>>),
 }
 
+/// Shared proxy state: the upstream client, the metrics handles, the routing table and the
+/// request/response header deny lists, passed to every request through axum's `State`.
+#[derive(Clone)]
+struct AppState {
+    client: ClientEnum,
+    metrics: Metrics,
+    routes: Arc<Vec<Route>>,
+    request_header_deny: Arc<Vec<String>>,
+    response_header_deny: Arc<Vec<String>>,
+}
+
+/// Hop-by-hop headers that must never be forwarded across a proxy (RFC 7230 §6.1).
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Remove hop-by-hop headers, anything prefixed `proxy-`, and every name in the configured
+/// deny list from `headers`. Names compare case-insensitively.
+fn sanitize_headers(headers: &mut axum::http::HeaderMap, deny: &[String]) {
+    let drop: Vec<_> = headers
+        .keys()
+        .filter(|name| {
+            let n = name.as_str().to_ascii_lowercase();
+            HOP_BY_HOP.contains(&n.as_str())
+                || n.starts_with("proxy-")
+                || deny.iter().any(|d| d == &n)
+        })
+        .cloned()
+        .collect();
+    for name in drop {
+        headers.remove(&name);
+    }
+}
+
+/// Parse a comma-separated, lower-cased deny list from `var`.
+fn load_header_deny(var: &str) -> Vec<String> {
+    env::var(var)
+        .ok()
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A single forwarding rule: requests whose path starts with `prefix` are rewritten onto
+/// `upstream`, optionally injecting a server-side `Authorization` header.
+#[derive(Clone, Debug, serde::Deserialize)]
+struct Route {
+    prefix: String,
+    upstream: String,
+    #[serde(default)]
+    authorization: Option<String>,
+}
+
+/// Load the routing table from `openai_proxy_routes_file` (a JSON file) or the
+/// `openai_proxy_routes` env var (inline JSON), defaulting to the original single
+/// `/openai/` → OpenAI rule when neither is set.
+fn load_routes() -> Vec<Route> {
+    let raw = if let Ok(path) = env::var("openai_proxy_routes_file") {
+        Some(std::fs::read_to_string(path).unwrap())
+    } else {
+        env::var("openai_proxy_routes").ok()
+    };
+    match raw {
+        Some(s) => serde_json::from_str(&s).unwrap(),
+        None => vec![Route {
+            prefix: "/openai/".to_string(),
+            upstream: "https://api.openai.com".to_string(),
+            authorization: None,
+        }],
+    }
+}
+
+/// Request/response metrics for proxied traffic, modeled on the counters-plus-latency
+/// histogram layout a front-end proxy usually exposes. The `Registry` is kept so the
+/// `/metrics` endpoint can render the current snapshot in the Prometheus text format.
+#[derive(Clone)]
+struct Metrics {
+    registry: Registry,
+    requests_received: Counter<u64>,
+    requests_served: Counter<u64>,
+    upstream_latency: Histogram<f64>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()
+            .unwrap();
+        use opentelemetry::metrics::MeterProvider as _;
+        let provider = opentelemetry_sdk::metrics::MeterProvider::builder()
+            .with_reader(exporter)
+            .build();
+        let meter: Meter = provider.meter("openai_proxy");
+        let requests_received = meter
+            .u64_counter("openai_proxy_requests_received")
+            .with_description("requests received on the forwarding branch")
+            .init();
+        let requests_served = meter
+            .u64_counter("openai_proxy_requests_served")
+            .with_description("responses returned to clients")
+            .init();
+        let upstream_latency = meter
+            .f64_histogram("openai_proxy_upstream_latency_ms")
+            .with_description("upstream round-trip latency in milliseconds")
+            .init();
+        Metrics {
+            registry,
+            requests_received,
+            requests_served,
+            upstream_latency,
+        }
+    }
+
+    /// Render the current metrics in the Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut buf = Vec::new();
+        let encoder = TextEncoder::new();
+        encoder
+            .encode(&self.registry.gather(), &mut buf)
+            .expect("encode prometheus metrics");
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// A typed upstream failure that maps onto a proper gateway status code instead of the
+/// old unit error that collapsed everything into a generic response.
+#[derive(Debug)]
+enum ProxyError {
+    Timeout,
+    Upstream,
+}
+
+impl ProxyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ProxyError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ProxyError::Upstream => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> axum::response::Response {
+        let status = self.status();
+        let msg = match self {
+            ProxyError::Timeout => "upstream timed out",
+            ProxyError::Upstream => "upstream request failed",
+        };
+        (status, msg).into_response()
+    }
+}
+
+/// Send the request upstream with a timeout on each attempt, retrying connection-level
+/// failures a bounded number of times with exponential backoff. A timed-out attempt is not
+/// retried. `build` is called once per attempt to produce a fresh body.
+async fn send_upstream(
+    client: &ClientEnum,
+    uri: &str,
+    build: impl Fn() -> Request<Body>,
+) -> Result<Response<Body>, ProxyError> {
+    let timeout = Duration::from_millis(
+        env::var("openai_proxy_timeout_ms")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60_000),
+    );
+    let max_retries = env::var("openai_proxy_max_retries")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1u32);
+
+    let mut attempt = 0u32;
+    loop {
+        let req = build();
+        match tokio::time::timeout(timeout, send_once(client, req)).await {
+            Err(_) => {
+                warn!("{} timed out after {:?}", uri, timeout);
+                return Err(ProxyError::Timeout);
+            }
+            Ok(Ok(resp)) => return Ok(resp),
+            Ok(Err(e)) => {
+                if attempt < max_retries && e.is_connect() {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(50 * 2u64.pow(attempt));
+                    warn!(
+                        "{} connection error (attempt {}), retrying in {:?}: {}",
+                        uri, attempt, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    continue;
+                }
+                warn!("{} error:{}", uri, e);
+                return Err(ProxyError::Upstream);
+            }
+        }
+    }
+}
+
+async fn send_once(
+    client: &ClientEnum,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    match client {
+        ClientEnum::Proxy(c) => {
+            check(c);
+            c.request(req).await
+        }
+        ClientEnum::Http(c) => {
+            check(c);
+            c.request(req).await
+        }
+    }
+}
+
+/// Bucket a status code into the conventional `2xx`/`4xx`/... class used as a metric label.
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() / 100 {
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        5 => "5xx",
+        _ => "other",
+    }
+}
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::registry()
@@ -43,75 +301,246 @@ async fn main() {
             let proxy_uri = proxy.parse().unwrap();
             let proxy = Proxy::new(Intercept::All, proxy_uri);
             // proxy.set_authorization(Authorization::basic("", ""));
-            let proxy_connector = ProxyConnector::from_proxy(https, proxy).unwrap();
-            proxy_connector
+            ProxyConnector::from_proxy(https, proxy).unwrap()
         };
-        ClientEnum::Proxy(Client::builder().build::<_, hyper::Body>(proxy))
+        ClientEnum::Proxy(
+            Client::builder()
+                .pool_idle_timeout(Duration::from_secs(90))
+                .pool_max_idle_per_host(32)
+                .build::<_, hyper::Body>(proxy),
+        )
     } else {
-        ClientEnum::Http(Client::builder().build::<_, hyper::Body>(https))
+        ClientEnum::Http(
+            Client::builder()
+                .pool_idle_timeout(Duration::from_secs(90))
+                .pool_max_idle_per_host(32)
+                .build::<_, hyper::Body>(https),
+        )
+    };
+
+    let state = AppState {
+        client,
+        metrics: Metrics::new(),
+        routes: Arc::new(load_routes()),
+        request_header_deny: Arc::new(load_header_deny("openai_proxy_request_header_deny")),
+        response_header_deny: Arc::new(load_header_deny("openai_proxy_response_header_deny")),
     };
 
     let app = Router::new()
+        .route("/metrics", axum::routing::get(metrics_handler))
         .route("/*path", any(handler))
-        .with_state(client);
+        .with_state(state);
 
     let port = env::var("openai_proxy_port")
         .map(|e| e.parse().unwrap())
         .unwrap_or(4000);
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    info!("reverse proxy listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    if env::var("openai_proxy_tls").is_ok() {
+        serve_https(addr, app).await;
+    } else {
+        info!("reverse proxy listening on {}", addr);
+        axum::Server::bind(&addr)
+            .serve(app.into_make_service())
+            .await
+            .unwrap();
+    }
+}
+
+/// A certificate resolver that always hands out one preloaded cert+key, built from the
+/// configured PEM files or a generated self-signed pair.
+struct StaticCertResolver(Arc<CertifiedKey>);
+
+impl std::fmt::Debug for StaticCertResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StaticCertResolver")
+    }
+}
+
+impl ResolvesServerCert for StaticCertResolver {
+    fn resolve(&self, _hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+}
+
+/// Build the resolver from `openai_proxy_tls_cert` / `openai_proxy_tls_key`, falling back
+/// to an in-memory self-signed certificate so the TLS listener always has something to serve.
+fn cert_resolver() -> StaticCertResolver {
+    use rustls::{Certificate, PrivateKey};
+
+    let (cert_chain, key) = match (
+        env::var("openai_proxy_tls_cert"),
+        env::var("openai_proxy_tls_key"),
+    ) {
+        (Ok(cert_path), Ok(key_path)) => {
+            let mut cert_reader =
+                std::io::BufReader::new(std::fs::File::open(&cert_path).unwrap());
+            let certs = rustls_pemfile::certs(&mut cert_reader)
+                .unwrap()
+                .into_iter()
+                .map(Certificate)
+                .collect();
+            let mut key_reader = std::io::BufReader::new(std::fs::File::open(&key_path).unwrap());
+            let key = rustls_pemfile::pkcs8_private_keys(&mut key_reader)
+                .unwrap()
+                .remove(0);
+            (certs, PrivateKey(key))
+        }
+        _ => {
+            info!("no tls cert/key configured, generating a self-signed certificate");
+            let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            let key = PrivateKey(cert.serialize_private_key_der());
+            let certs = vec![Certificate(cert.serialize_der().unwrap())];
+            (certs, key)
+        }
+    };
+
+    let signing_key = rustls::sign::any_supported_type(&key).unwrap();
+    StaticCertResolver(Arc::new(CertifiedKey::new(cert_chain, signing_key)))
+}
+
+/// TLS-terminating listener: negotiates HTTP/2 then HTTP/1.1 via ALPN and serves the same
+/// `Router` used by the plain-HTTP path.
+async fn serve_https(addr: SocketAddr, app: Router) {
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(cert_resolver()));
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind(addr).await.unwrap();
+    info!("reverse proxy listening on https://{}", addr);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("accept error: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let tls = match acceptor.accept(stream).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    warn!("tls handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+            if let Err(e) = hyper::server::conn::Http::new()
+                .serve_connection(tls, app)
+                .with_upgrades()
+                .await
+            {
+                warn!("connection from {} error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(Body::from(state.metrics.render()))
+        .unwrap()
 }
 
 async fn handler(
-    State(client): State<ClientEnum>,
+    State(state): State<AppState>,
     mut req: Request<Body>,
-) -> Result<Response<Body>, ()> {
+) -> Result<Response<Body>, ProxyError> {
+    let AppState {
+        client,
+        metrics,
+        routes,
+        request_header_deny,
+        response_header_deny,
+    } = state;
     let path = req.uri().path();
-    if path.starts_with("/openai/") {
-        let path = path.replacen("/openai/", "/", 1);
-        let query = if let Some(q) = req.uri().query() {
-            q
-        } else {
-            ""
+    if let Some(route) = routes.iter().find(|r| path.starts_with(&r.prefix)) {
+        let path = path.replacen(&route.prefix, "/", 1);
+        let base = route.upstream.trim_end_matches('/');
+        // `query()` returns the part *after* the `?`, so re-add the separator ourselves and
+        // only when a query is actually present (Azure OpenAI needs its `api-version` param).
+        let uri = match req.uri().query() {
+            Some(q) => format!("{}{}?{}", base, &path, q),
+            None => format!("{}{}", base, &path),
         };
-        let uri = format!("https://api.openai.com{}{}", &path, query);
         info!("request to {}", uri);
-        req.headers_mut()
-            .insert("host", HeaderValue::from_static("api.openai.com"));
-        *req.uri_mut() = Uri::try_from(uri.clone()).unwrap();
+        let prefix = KeyValue::new("prefix", route.prefix.clone());
+        metrics
+            .requests_received
+            .add(1, std::slice::from_ref(&prefix));
+        let upstream = Uri::try_from(uri.clone()).unwrap();
+        // drop hop-by-hop and operator-denied headers before we add our own host/auth, so a
+        // denied `authorization` entry can't strip the key we are about to inject.
+        sanitize_headers(req.headers_mut(), &request_header_deny);
+        // rewrite host to the matched upstream's authority so the request is routed there.
+        if let Some(authority) = upstream.authority() {
+            req.headers_mut().insert(
+                "host",
+                HeaderValue::from_str(authority.as_str()).unwrap(),
+            );
+        }
+        // inject the server-side key for this route, if one is configured.
+        if let Some(auth) = &route.authorization {
+            req.headers_mut()
+                .insert("authorization", HeaderValue::from_str(auth).unwrap());
+        }
+        *req.uri_mut() = upstream;
 
-        req = read_body(req).await;
+        // buffer the body exactly once (read_body already reads it for logging) and keep the
+        // pieces so a connection-level failure can rebuild a fresh request — `Request` is not
+        // cloneable once its body is a stream.
+        let (parts, body_bytes, stream_requested) = read_body(req).await;
+        let method = parts.method;
+        let headers = parts.headers;
+        let upstream = parts.uri;
+        let build = || {
+            let mut b = Request::builder()
+                .method(method.clone())
+                .uri(upstream.clone());
+            *b.headers_mut().unwrap() = headers.clone();
+            b.body(Body::from(body_bytes.clone())).unwrap()
+        };
 
         let started = SystemTime::now();
-        let r = match client {
-            ClientEnum::Proxy(client) => {
-                check(&client);
-                client.request(req)
-            }
-            ClientEnum::Http(client) => {
-                check(&client);
-                client.request(req)
-            }
-        }
-        .await
-        .map_err(|e| {
-            warn!("{} error:{}", uri, e);
-            ()
-        });
-        info!(
-            "request to {}. time: {}ms",
-            uri,
-            started.elapsed().unwrap().as_millis()
+        let r = send_upstream(&client, &uri, build).await;
+        let elapsed = started.elapsed().unwrap();
+        info!("request to {}. time: {}ms", uri, elapsed.as_millis());
+        metrics
+            .upstream_latency
+            .record(elapsed.as_secs_f64() * 1000.0, std::slice::from_ref(&prefix));
+        let status = match &r {
+            Ok(resp) => resp.status(),
+            Err(e) => e.status(),
+        };
+        metrics.requests_served.add(
+            1,
+            &[prefix, KeyValue::new("status", status_class(status))],
         );
-        if let Ok(resp) = r {
-            let new_resp = read_response(resp).await;
-            Ok(new_resp)
+        if let Ok(mut resp) = r {
+            // strip hop-by-hop and operator-denied response headers before returning.
+            sanitize_headers(resp.headers_mut(), &response_header_deny);
+            // OpenAI's `stream: true` mode sends back `text/event-stream`; buffering the
+            // whole body there would hold every token until the upstream closed, so we
+            // forward those responses chunk-by-chunk and only tee the bytes for logging.
+            let is_sse = resp
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.starts_with("text/event-stream"))
+                .unwrap_or(false);
+            if is_sse || stream_requested {
+                Ok(stream_response(resp))
+            } else {
+                let new_resp = read_response(resp).await;
+                Ok(new_resp)
+            }
         } else {
-            r
+            Err(r.unwrap_err())
         }
     } else {
         Ok(Response::builder()
@@ -128,18 +557,75 @@ where
     info!("{:?}", c);
 }
 
-async fn read_body(mut req: Request<Body>) -> Request<Body> {
-    // code from https://stackoverflow.com/questions/75849660/how-to-use-the-body-of-a-hyperrequest-without-consuming-it
-    // destructure the request so we can get the body & other parts separately
+async fn read_body(
+    req: Request<Body>,
+) -> (hyper::http::request::Parts, hyper::body::Bytes, bool) {
+    // destructure the request so we can log & inspect the body, returning the buffered
+    // pieces so the caller can rebuild the request without buffering a second time.
     let (parts, body) = req.into_parts();
     let body_bytes = hyper::body::to_bytes(body).await.unwrap();
-    let body = std::str::from_utf8(&body_bytes).unwrap();
+    // request bodies aren't always UTF-8 (multipart audio, image uploads), so log lossily
+    // instead of panicking on them.
+    let body = String::from_utf8_lossy(&body_bytes);
 
     info!("request body:\n\n{}\n", body);
-    // reconstruct the Request from parts and the data in `body_bytes`
-    req = Request::from_parts(parts, body_bytes.into());
+    // Best-effort hint that the client asked for SSE; the response `content-type` check is
+    // the authoritative guard, so this substring match only needs to catch the common
+    // compact form and is allowed to miss pretty-printed or oddly-quoted bodies.
+    let stream_requested = body.contains("\"stream\":true") || body.contains("\"stream\": true");
 
-    return req;
+    (parts, body_bytes, stream_requested)
+}
+
+async fn decode_for_log(encoding: Option<&str>, body_bytes: &[u8]) -> std::io::Result<String> {
+    use async_compression::tokio::bufread::{
+        BrotliDecoder, DeflateDecoder, GzipDecoder, ZstdDecoder,
+    };
+    use tokio::io::AsyncReadExt;
+
+    let mut decoded = Vec::new();
+    match encoding {
+        Some("gzip") => {
+            GzipDecoder::new(body_bytes)
+                .read_to_end(&mut decoded)
+                .await?;
+        }
+        Some("deflate") => {
+            DeflateDecoder::new(body_bytes)
+                .read_to_end(&mut decoded)
+                .await?;
+        }
+        Some("br") => {
+            BrotliDecoder::new(body_bytes)
+                .read_to_end(&mut decoded)
+                .await?;
+        }
+        Some("zstd") => {
+            ZstdDecoder::new(body_bytes)
+                .read_to_end(&mut decoded)
+                .await?;
+        }
+        // `identity`, an absent header, or anything we do not recognise: pass through.
+        _ => decoded.extend_from_slice(body_bytes),
+    }
+    Ok(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+fn stream_response(resp: Response<Body>) -> Response<Body> {
+    // forward the upstream body as it arrives, teeing each chunk into the log so we
+    // keep the per-`data:` tracing without introducing head-of-line buffering.
+    let (parts, body) = resp.into_parts();
+    let logged = body.map(|chunk| {
+        if let Ok(bytes) = &chunk {
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                for line in text.lines().filter(|l| l.starts_with("data:")) {
+                    info!("sse {}", line);
+                }
+            }
+        }
+        chunk
+    });
+    Response::from_parts(parts, Body::wrap_stream(logged))
 }
 
 async fn read_response(mut resp: Response<Body>) -> Response<Body> {
@@ -149,18 +635,16 @@ async fn read_response(mut resp: Response<Body>) -> Response<Body> {
     // info!("parts: {:?}", parts);
     let body_bytes = hyper::body::to_bytes(body).await.unwrap();
 
-    use std::io::Read;
-
-    if parts.headers.get("content-encoding").is_some() {
-        let mut decoder = Decoder::new(&body_bytes[..]).unwrap();
-        let mut decoded_data = Vec::new();
-        decoder.read_to_end(&mut decoded_data).unwrap();
-        // println!("{:?}", body_bytes);
-        let body = String::from_utf8(decoded_data).unwrap();
-        info!("response:\n\n{}\n", body);
-    } else {
-        let body = std::str::from_utf8(&body_bytes).unwrap();
-        info!("response:\n\n{}\n", body);
+    let encoding = parts
+        .headers
+        .get("content-encoding")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase());
+    // decode only so the log is readable — the original compressed bytes are still
+    // forwarded untouched below, so the client keeps whatever encoding it negotiated.
+    match decode_for_log(encoding.as_deref(), &body_bytes).await {
+        Ok(body) => info!("response:\n\n{}\n", body),
+        Err(e) => warn!("could not decode response body for logging: {}", e),
     }
     // now we have all data so we just disable chunk and send all data
     parts.headers.remove("transfer-encoding");
@@ -169,5 +653,5 @@ async fn read_response(mut resp: Response<Body>) -> Response<Body> {
         .insert("Content-Length", HeaderValue::from(body_bytes.len()));
     resp = Response::from_parts(parts, body_bytes.into());
 
-    return resp;
+    resp
 }